@@ -0,0 +1,184 @@
+use std::sync::{
+    atomic::{
+        AtomicUsize,
+        Ordering::{Acquire, Relaxed, Release},
+    },
+    Arc,
+};
+
+use crate::{queue_based_lock::Queue, semaphore::Semaphore};
+
+/// A bounded multi-producer, single-consumer channel: `Queue<T>` carries
+/// the values, a `capacity` semaphore throttles producers (one permit per
+/// free slot), and an `items` semaphore wakes the consumer when something
+/// is pushed (one permit per pending item).
+struct Channel<T> {
+    queue: Queue<T>,
+    capacity: Semaphore,
+    items: Semaphore,
+    senders: AtomicUsize,
+}
+
+pub struct Sender<T> {
+    channel: Arc<Channel<T>>,
+}
+
+pub struct Receiver<T> {
+    channel: Arc<Channel<T>>,
+}
+
+pub fn channel<T>(capacity: u32) -> (Sender<T>, Receiver<T>) {
+    let channel = Arc::new(Channel {
+        queue: Queue::new(),
+        capacity: Semaphore::new(capacity),
+        items: Semaphore::new(0),
+        senders: AtomicUsize::new(1),
+    });
+    (
+        Sender {
+            channel: channel.clone(),
+        },
+        Receiver { channel },
+    )
+}
+
+impl<T> Sender<T> {
+    /// Blocks until the channel has a free slot, then pushes `value`.
+    pub fn send(&self, value: T) {
+        let permit = self
+            .channel
+            .capacity
+            .aquire()
+            .expect("a channel's capacity semaphore is never closed");
+        // The slot this permit represents now belongs to the item sitting
+        // in the queue; it is handed back by `Receiver::recv` once the
+        // item is taken out, not here.
+        std::mem::forget(permit);
+
+        self.channel.queue.push(value);
+        self.channel.items.add_permits(1);
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.channel.senders.fetch_add(1, Relaxed);
+        Sender {
+            channel: self.channel.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if self.channel.senders.fetch_sub(1, Release) == 1 {
+            std::sync::atomic::fence(Acquire);
+            // Wake the receiver's empty wait; any items already in the
+            // queue are still drained by `recv`'s direct pop below.
+            self.channel.items.close();
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Pops the next value, blocking while the channel is empty. Returns
+    /// `None` once every `Sender` has dropped and the queue has drained.
+    pub fn recv(&self) -> Option<T> {
+        match self.channel.items.aquire() {
+            // `items` holds one permit per pushed-but-not-yet-popped value,
+            // so a granted permit guarantees `pop` finds something: there
+            // is no direct-pop fast path ahead of this acquire, or `items`'s
+            // count would drift away from the queue's actual length.
+            Ok(permit) => {
+                std::mem::forget(permit);
+                let value = self
+                    .channel
+                    .queue
+                    .pop()
+                    .expect("a granted `items` permit represents a value already in the queue");
+                self.channel.capacity.add_permits(1);
+                Some(value)
+            }
+            Err(_closed) => match self.channel.queue.pop() {
+                Some(value) => {
+                    self.channel.capacity.add_permits(1);
+                    Some(value)
+                }
+                None => None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn send_then_recv() {
+        let (tx, rx) = channel(4);
+        tx.send(1);
+        tx.send(2);
+        assert_eq!(rx.recv(), Some(1));
+        assert_eq!(rx.recv(), Some(2));
+    }
+
+    #[test]
+    fn recv_returns_none_after_last_sender_drops() {
+        let (tx, rx) = channel::<i32>(4);
+        drop(tx);
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    fn recv_drains_remaining_items_after_senders_drop() {
+        let (tx, rx) = channel(4);
+        tx.send(1);
+        tx.send(2);
+        drop(tx);
+        assert_eq!(rx.recv(), Some(1));
+        assert_eq!(rx.recv(), Some(2));
+        assert_eq!(rx.recv(), None);
+    }
+
+    #[test]
+    fn backpressure_blocks_producer_until_consumer_catches_up() {
+        let (tx, rx) = channel(1);
+        tx.send(1);
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                tx.send(2);
+            });
+            assert_eq!(rx.recv(), Some(1));
+            assert_eq!(rx.recv(), Some(2));
+        });
+    }
+
+    #[test]
+    fn multiple_producers() {
+        const NUM_SENDERS: usize = 8;
+        const NUM_PER_SENDER: usize = 100;
+
+        let (tx, rx) = channel(16);
+        thread::scope(|s| {
+            for i in 0..NUM_SENDERS {
+                let tx = tx.clone();
+                s.spawn(move || {
+                    for j in 0..NUM_PER_SENDER {
+                        tx.send(i * NUM_PER_SENDER + j);
+                    }
+                });
+            }
+            drop(tx);
+
+            let mut received = 0;
+            while rx.recv().is_some() {
+                received += 1;
+            }
+            assert_eq!(received, NUM_SENDERS * NUM_PER_SENDER);
+        });
+    }
+}
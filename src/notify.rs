@@ -0,0 +1,186 @@
+use std::sync::atomic::{
+    AtomicU32,
+    Ordering::{Acquire, Relaxed, Release},
+};
+
+use atomic_wait::{wait, wake_all, wake_one};
+
+const IDLE: u32 = 0;
+const NOTIFIED: u32 = 1;
+
+/// A one-off thread-parking signal, distinct from the mutex-style
+/// [`BinarySemaphore`](crate::semaphore::binary_semaphore::BinarySemaphore):
+/// a `notify_one()` that arrives before any thread is waiting is
+/// remembered, so the next `wait()` returns immediately instead of
+/// deadlocking.
+pub struct Notify {
+    // Stores a single pending notification (`IDLE` or `NOTIFIED`), and is
+    // also the exact word waiters park on: `atomic_wait`'s `wait()` only
+    // actually parks if the word is still `IDLE` at syscall entry, so a
+    // `notify_one()` landing anywhere up to the instant a waiter parks is
+    // guaranteed not to be missed.
+    state: AtomicU32,
+    // Bumped (only) by `notify_all`, so a waiter that wakes can tell a
+    // broadcast happened - which every waiter must return for - apart from
+    // a `notify_one` hand-off, which only the `state` CAS winner should
+    // return for, or a plain spurious wake, which should just go back to
+    // sleep.
+    generation: AtomicU32,
+}
+
+impl Default for Notify {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Notify {
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU32::new(IDLE),
+            generation: AtomicU32::new(0),
+        }
+    }
+
+    /// Blocks until a notification is received. If one is already stored
+    /// (from a `notify_one()` that ran before this call), it is consumed
+    /// and this returns immediately without parking.
+    pub fn wait(&self) {
+        loop {
+            // Checked immediately before every park call (not just once,
+            // up front): `state` is the exact word we block on, so a
+            // `notify_one` landing anywhere up to the instant we actually
+            // park is guaranteed to be visible to `wait`'s own atomic
+            // recheck below, which only actually parks if `state` is
+            // still `IDLE` at that point.
+            if self
+                .state
+                .compare_exchange(NOTIFIED, IDLE, Acquire, Relaxed)
+                .is_ok()
+            {
+                return;
+            }
+
+            let generation = self.generation.load(Acquire);
+            wait(&self.state, IDLE);
+
+            if self.generation.load(Acquire) != generation {
+                // A `notify_all` happened while we were parked (or in the
+                // race window just before parking): every waiter returns
+                // for this, not just whichever one (if any) also wins the
+                // `state` CAS above, so opportunistically consume a
+                // stored permit if there is one, rather than leaking it
+                // to some unrelated later `wait()`, but return either way.
+                self.state
+                    .compare_exchange(NOTIFIED, IDLE, Acquire, Relaxed)
+                    .ok();
+                return;
+            }
+            // Either a spurious wake, or a `notify_one` we lost the race
+            // for `state` to another waiter: go back around and wait for
+            // the next one.
+        }
+    }
+
+    /// Stores a notification and wakes at most one parked waiter, if any.
+    pub fn notify_one(&self) {
+        self.state.store(NOTIFIED, Release);
+        wake_one(&self.state);
+    }
+
+    /// Wakes every currently parked waiter. Unlike `notify_one`, this does
+    /// not store anything for a `wait()` that starts afterwards.
+    pub fn notify_all(&self) {
+        self.generation.fetch_add(1, Release);
+        wake_all(&self.state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{atomic::AtomicUsize, Arc},
+        thread,
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[test]
+    fn notify_before_wait_is_not_lost() {
+        let notify = Notify::new();
+        notify.notify_one();
+        notify.wait();
+    }
+
+    #[test]
+    fn notify_wakes_parked_waiter() {
+        let notify = Arc::new(Notify::new());
+        thread::scope(|s| {
+            let waiter = notify.clone();
+            let handle = s.spawn(move || waiter.wait());
+
+            thread::sleep(Duration::from_millis(50));
+            notify.notify_one();
+            handle.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn notify_one_wakes_at_most_one_waiter() {
+        const NUM_WAITERS: usize = 4;
+
+        let notify = Arc::new(Notify::new());
+        let awake = Arc::new(AtomicUsize::new(0));
+
+        thread::scope(|s| {
+            let handles: Vec<_> = (0..NUM_WAITERS)
+                .map(|_| {
+                    let notify = notify.clone();
+                    let awake = awake.clone();
+                    s.spawn(move || {
+                        notify.wait();
+                        awake.fetch_add(1, Relaxed);
+                    })
+                })
+                .collect();
+
+            thread::sleep(Duration::from_millis(50));
+            notify.notify_one();
+            thread::sleep(Duration::from_millis(50));
+            assert_eq!(
+                awake.load(Relaxed),
+                1,
+                "notify_one must wake exactly one waiter, not every parked one"
+            );
+
+            // Release the rest so the scope doesn't hang on join.
+            notify.notify_all();
+            for handle in handles {
+                handle.join().unwrap();
+            }
+        });
+    }
+
+    #[test]
+    fn notify_all_wakes_every_waiter() {
+        const NUM_WAITERS: usize = 10;
+
+        let notify = Arc::new(Notify::new());
+        thread::scope(|s| {
+            let handles: Vec<_> = (0..NUM_WAITERS)
+                .map(|_| {
+                    let notify = notify.clone();
+                    s.spawn(move || notify.wait())
+                })
+                .collect();
+
+            thread::sleep(Duration::from_millis(50));
+            notify.notify_all();
+
+            for handle in handles {
+                handle.join().unwrap();
+            }
+        });
+    }
+}
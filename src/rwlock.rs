@@ -0,0 +1,159 @@
+use std::{
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+};
+
+use crate::semaphore::{Permit, Semaphore};
+
+/// Upper bound on concurrent readers. A `write()` call drains all of these
+/// permits at once, so this is also the number of permits a writer has to
+/// wait for.
+const MAX_READERS: u32 = u16::MAX as u32;
+
+/// A reader-writer lock built on the counting [`Semaphore`]: readers each
+/// hold one permit, a writer holds all `MAX_READERS` of them, so readers
+/// and the writer can never be let in at the same time.
+///
+/// `Semaphore` is not fair: `write()`'s `aquire_n(MAX_READERS)` only
+/// succeeds once every permit is simultaneously free, so under a steady
+/// stream of readers a writer can in principle wait indefinitely. Prefer
+/// this for read-heavy state where writes are rare enough that this
+/// doesn't matter; switching to the fair-queued
+/// [`FairSemaphore`](crate::semaphore::fair_semaphore::FairSemaphore)
+/// would close the gap at the cost of the read path's lock-free fast path.
+pub struct RwLock<T> {
+    semaphore: Semaphore,
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T> Sync for RwLock<T> where T: Send + Sync {}
+
+pub struct RwLockReadGuard<'a, T> {
+    permit: Permit<'a>,
+    lock: &'a RwLock<T>,
+}
+
+pub struct RwLockWriteGuard<'a, T> {
+    permit: Permit<'a>,
+    lock: &'a RwLock<T>,
+}
+
+impl<T> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<T> RwLock<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            semaphore: Semaphore::new(MAX_READERS),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        let permit = self
+            .semaphore
+            .aquire()
+            .expect("RwLock's semaphore is never closed");
+        RwLockReadGuard {
+            permit,
+            lock: self,
+        }
+    }
+
+    /// Blocks until every reader has released its permit. See the
+    /// struct-level docs: a continuous stream of readers can starve this
+    /// indefinitely, since the underlying `Semaphore` serves whichever
+    /// request's permits happen to be available, not in arrival order.
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        let permit = self
+            .semaphore
+            .aquire_n(MAX_READERS)
+            .expect("RwLock's semaphore is never closed");
+        RwLockWriteGuard {
+            permit,
+            lock: self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Barrier},
+        thread,
+    };
+
+    use super::*;
+
+    #[test]
+    fn read_uncontended() {
+        let lock = RwLock::new(5);
+        assert_eq!(*lock.read(), 5);
+    }
+
+    #[test]
+    fn concurrent_reads() {
+        let lock = Arc::new(RwLock::new(5));
+        thread::scope(|s| {
+            for _ in 0..10 {
+                let lock = lock.clone();
+                s.spawn(move || {
+                    assert_eq!(*lock.read(), 5);
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn write_excludes_readers() {
+        const NUM_READERS: usize = 10;
+        const NUM_ITERATIONS: usize = 100;
+
+        let lock = Arc::new(RwLock::new(0));
+        let barrier = Arc::new(Barrier::new(NUM_READERS + 1));
+
+        thread::scope(|s| {
+            for _ in 0..NUM_READERS {
+                let lock = lock.clone();
+                let barrier = barrier.clone();
+                s.spawn(move || {
+                    barrier.wait();
+                    for _ in 0..NUM_ITERATIONS {
+                        let value = *lock.read();
+                        assert_eq!(value % 2, 0);
+                    }
+                });
+            }
+
+            let lock = lock.clone();
+            let barrier = barrier.clone();
+            s.spawn(move || {
+                barrier.wait();
+                for _ in 0..NUM_ITERATIONS {
+                    let mut guard = lock.write();
+                    *guard += 1;
+                    *guard += 1;
+                }
+            });
+        });
+
+        assert_eq!(*lock.read(), NUM_ITERATIONS * 2);
+    }
+}
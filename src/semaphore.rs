@@ -1,11 +1,25 @@
-use std::sync::atomic::{
-    AtomicU32,
-    Ordering::{Acquire, Relaxed, Release},
+use std::sync::{
+    atomic::{
+        AtomicU32,
+        Ordering::{Acquire, Relaxed, Release},
+    },
+    Arc,
 };
 
-use atomic_wait::{wait, wake_one};
+use atomic_wait::{wait, wake_all, wake_one};
 
 pub mod binary_semaphore;
+pub mod fair_semaphore;
+
+/// Set once [`Semaphore::close`] has been called. Kept in the top bit of
+/// `state` so the fast-path CAS loops only need one extra bit test on the
+/// value they already loaded, not a second atomic.
+const CLOSED: u32 = 1 << 31;
+
+/// Returned by `aquire`/`aquire_n` once the semaphore has been closed:
+/// the standard shutdown signal for pools and channels built on top of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Closed;
 
 pub struct Semaphore {
     state: AtomicU32,
@@ -17,6 +31,14 @@ pub struct Permit<'a> {
     semaphore: &'a Semaphore,
 }
 
+/// A permit that owns an `Arc` clone of its semaphore rather than
+/// borrowing it, so it is `'static` and can outlive the scope that
+/// acquired it.
+pub struct OwnedPermit {
+    state: u32,
+    semaphore: Arc<Semaphore>,
+}
+
 impl Semaphore {
     pub const fn new(value: u32) -> Self {
         Self {
@@ -25,66 +47,102 @@ impl Semaphore {
         }
     }
 
-    pub fn aquire(&self) -> Permit {
-        let mut s = self.state.load(Relaxed);
-        loop {
-            if s > 0 {
-                match self.state.compare_exchange_weak(s, s - 1, Acquire, Relaxed) {
-                    Ok(_) => {
-                        return Permit {
-                            state: 1,
-                            semaphore: self,
-                        }
-                    }
-                    Err(e) => s = e,
-                }
-            }
-            if s == 0 {
-                while self.state.load(Relaxed) == 0 {
-                    wait(&self.state, 0);
-                }
-            }
-        }
+    pub fn aquire(&self) -> Result<Permit, Closed> {
+        self.acquire_raw(1).map(|state| Permit {
+            state,
+            semaphore: self,
+        })
+    }
+
+    pub fn aquire_n(&self, n: u32) -> Result<Permit, Closed> {
+        assert!(
+            n <= self.max_permits,
+            "Cannot aquire more than the maximum number of permits."
+        );
+        self.acquire_raw(n).map(|state| Permit {
+            state,
+            semaphore: self,
+        })
+    }
+
+    /// Like [`Semaphore::aquire`], but the returned permit takes ownership
+    /// of the `Arc` instead of borrowing `self`, so it is `'static` and can
+    /// be moved into a spawned thread or stored alongside long-lived work.
+    pub fn aquire_owned(self: Arc<Self>) -> Result<OwnedPermit, Closed> {
+        let state = self.acquire_raw(1)?;
+        Ok(OwnedPermit {
+            state,
+            semaphore: self,
+        })
     }
 
-    pub fn aquire_n(&self, n: u32) -> Permit {
+    /// `Arc`-owning counterpart to [`Semaphore::aquire_n`].
+    pub fn aquire_n_owned(self: Arc<Self>, n: u32) -> Result<OwnedPermit, Closed> {
         assert!(
             n <= self.max_permits,
             "Cannot aquire more than the maximum number of permits."
         );
+        let state = self.acquire_raw(n)?;
+        Ok(OwnedPermit {
+            state,
+            semaphore: self,
+        })
+    }
+
+    pub fn release(permit: Permit) {
+        drop(permit);
+    }
+
+    fn acquire_raw(&self, n: u32) -> Result<u32, Closed> {
         let mut s = self.state.load(Relaxed);
         loop {
+            if s & CLOSED != 0 {
+                return Err(Closed);
+            }
             if s >= n {
                 match self.state.compare_exchange_weak(s, s - n, Acquire, Relaxed) {
-                    Ok(_) => {
-                        return Permit {
-                            state: n,
-                            semaphore: self,
-                        }
-                    }
+                    Ok(_) => return Ok(n),
                     Err(e) => s = e,
                 }
+                continue;
             }
-            if s < n {
-                wait(&self.state, s);
-                s = self.state.load(Relaxed);
-            }
+            wait(&self.state, s);
+            s = self.state.load(Relaxed);
         }
     }
 
-    pub fn release(permit: Permit) {
-        drop(permit);
+    /// Close the semaphore: every currently parked acquirer wakes with
+    /// `Err(Closed)`, and all present and future `aquire`/`aquire_n` calls
+    /// fail immediately instead of parking.
+    pub fn close(&self) {
+        self.state.fetch_or(CLOSED, Release);
+        wake_all(&self.state);
     }
 
     fn get_count(&self) -> u32 {
-        self.state.load(Relaxed)
+        self.state.load(Relaxed) & !CLOSED
+    }
+
+    /// Hand `n` permits back to the semaphore without having gone through
+    /// an acquired [`Permit`]/[`OwnedPermit`] first. Used internally by
+    /// `Permit`/`OwnedPermit`'s `Drop`, and by composed primitives (like a
+    /// channel) that move a permit's "ownership" of a slot from one side
+    /// of the primitive to the other.
+    pub(crate) fn add_permits(&self, n: u32) {
+        self.state.fetch_add(n, Release);
+        wake_one(&self.state);
     }
 }
 
 impl Drop for Permit<'_> {
     fn drop(&mut self) {
-        self.semaphore.state.fetch_add(self.state, Release);
-        wake_one(&self.semaphore.state);
+        self.semaphore.add_permits(self.state);
+    }
+}
+
+impl Drop for OwnedPermit {
+    fn drop(&mut self) {
+        self.semaphore.add_permits(self.state);
     }
 }
 
@@ -109,7 +167,7 @@ mod tests {
                 s.spawn(|| {
                     let semaphore = semaphore.clone();
                     {
-                        let permit = semaphore.aquire_n(i.into_inner());
+                        let permit = semaphore.aquire_n(i.into_inner()).unwrap();
                         std::hint::black_box(&semaphore);
                         assert!(semaphore.state.load(Relaxed) <= 30);
                         Semaphore::release(permit);
@@ -119,4 +177,36 @@ mod tests {
         });
         assert_eq!(semaphore.get_count(), 30);
     }
+
+    #[test]
+    fn close_wakes_parked_waiters_with_err() {
+        let semaphore = Arc::new(Semaphore::new(0));
+        thread::scope(|s| {
+            let waiter = semaphore.clone();
+            // Returns whether the acquire failed, not the `Permit` itself:
+            // it borrows `waiter`, which is owned by this closure and does
+            // not outlive it.
+            let handle = s.spawn(move || waiter.aquire().is_err());
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            semaphore.close();
+            assert!(handle.join().unwrap());
+        });
+        assert!(semaphore.aquire().is_err());
+    }
+
+    #[test]
+    fn owned_permit_outlives_acquiring_scope() {
+        let semaphore = Arc::new(Semaphore::new(1));
+        let handle = {
+            let semaphore = semaphore.clone();
+            thread::spawn(move || {
+                let permit = semaphore.aquire_owned().unwrap();
+                std::hint::black_box(&permit);
+                permit
+            })
+        };
+        let permit = handle.join().unwrap();
+        drop(permit);
+        assert_eq!(semaphore.get_count(), 1);
+    }
 }
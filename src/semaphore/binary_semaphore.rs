@@ -48,7 +48,6 @@ fn lock_contended(state: &AtomicU32) {
     let mut spin_count = 0;
     while state.load(Relaxed) == 1 && spin_count < 100 {
         spin_count += 1;
-        loom::hint::spin_loop();
         std::hint::spin_loop();
     }
 
@@ -57,7 +56,7 @@ fn lock_contended(state: &AtomicU32) {
     }
 
     while state.swap(0, Acquire) != 2 {
-        loom::hint::spin_loop();
+        std::hint::spin_loop();
         wait(state, 0);
     }
 }
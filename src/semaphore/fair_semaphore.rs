@@ -0,0 +1,314 @@
+use std::{
+    ptr::null_mut,
+    sync::atomic::{
+        AtomicBool, AtomicU32,
+        Ordering::{Acquire, Relaxed, Release},
+    },
+};
+
+use atomic_wait::{wait, wake_one};
+
+use crate::mutex::Mutex;
+
+/// A counting semaphore that hands permits to waiters in strict arrival
+/// order, so a request for a large `n` cannot be starved by a steady
+/// stream of smaller requests.
+///
+/// The fast path (queue empty, enough permits available) is still a plain
+/// `compare_exchange_weak` loop on `state`. Once a thread has to block, it
+/// is linked into an intrusive, `Mutex`-guarded FIFO of waiter nodes, and
+/// `release` only ever hands permits to the front of that queue.
+pub struct FairSemaphore {
+    state: AtomicU32,
+    max_permits: u32,
+    waiters: Mutex<WaiterQueue>,
+    // Mirrors "is `waiters`'s queue non-empty", readable without taking the
+    // `waiters` lock, so the fast path can tell whether it's allowed to
+    // race ahead of a parked waiter without paying for the lock on every
+    // call. Set (under the lock) whenever a node is linked in, cleared
+    // (under the lock) when the last node is unlinked.
+    has_waiters: AtomicBool,
+}
+
+pub struct Permit<'a> {
+    state: u32,
+    semaphore: &'a FairSemaphore,
+}
+
+/// Intrusive singly-linked list of waiter nodes. Nodes are stack-allocated
+/// by the blocking thread and unlinked before it resumes, so the list never
+/// owns them.
+struct WaiterQueue {
+    head: *mut WaiterNode,
+    tail: *mut WaiterNode,
+}
+
+// SAFETY: the raw pointers in `WaiterQueue` only ever point at `WaiterNode`s
+// owned by other threads' stack frames, and all access to them happens
+// while holding `FairSemaphore::waiters`.
+unsafe impl Send for WaiterQueue {}
+
+struct WaiterNode {
+    needed: u32,
+    // 0 while parked, 1 once `release` has handed this waiter its permits.
+    futex: AtomicU32,
+    next: *mut WaiterNode,
+}
+
+impl FairSemaphore {
+    pub const fn new(value: u32) -> Self {
+        Self {
+            state: AtomicU32::new(value),
+            max_permits: value,
+            waiters: Mutex::new(WaiterQueue {
+                head: null_mut(),
+                tail: null_mut(),
+            }),
+            has_waiters: AtomicBool::new(false),
+        }
+    }
+
+    pub fn aquire(&self) -> Permit {
+        self.aquire_n(1)
+    }
+
+    /// Bare CAS loop against `state`: claims `n` permits if they're
+    /// immediately available, without touching `waiters`.
+    fn try_aquire_n(&self, n: u32) -> Option<Permit> {
+        let mut s = self.state.load(Relaxed);
+        loop {
+            if s < n {
+                return None;
+            }
+            match self.state.compare_exchange_weak(s, s - n, Acquire, Relaxed) {
+                Ok(_) => {
+                    return Some(Permit {
+                        state: n,
+                        semaphore: self,
+                    })
+                }
+                Err(e) => s = e,
+            }
+        }
+    }
+
+    pub fn aquire_n(&self, n: u32) -> Permit {
+        assert!(
+            n <= self.max_permits,
+            "Cannot aquire more than the maximum number of permits."
+        );
+
+        // Fast path: a plain CAS loop on `state`, with no lock involved,
+        // taken only while nobody is already queued ahead of us - otherwise
+        // a steady stream of small requests could keep winning released
+        // permits forever and starve an already-parked large request, which
+        // is exactly what the queue exists to prevent. `has_waiters` is
+        // just a lock-free mirror of "is the queue non-empty", so checking
+        // it doesn't cost the `waiters` lock on every call.
+        if !self.has_waiters.load(Acquire) {
+            if let Some(permit) = self.try_aquire_n(n) {
+                return permit;
+            }
+        }
+
+        let mut queue = self.waiters.lock();
+
+        // Permits may have arrived between the fast path above and taking
+        // the lock. Retry once more while holding it, but only if nobody
+        // is already queued ahead of us - otherwise a small request could
+        // cut in front of a larger one that arrived first.
+        if queue.head.is_null() {
+            if let Some(permit) = self.try_aquire_n(n) {
+                return permit;
+            }
+        }
+
+        let mut node = WaiterNode {
+            needed: n,
+            futex: AtomicU32::new(0),
+            next: null_mut(),
+        };
+        let node_ptr: *mut WaiterNode = &mut node;
+
+        if queue.tail.is_null() {
+            queue.head = node_ptr;
+        } else {
+            // SAFETY: `tail` belongs to a thread still parked below; nodes
+            // are only unlinked under the queue lock, which we hold here.
+            unsafe { (*queue.tail).next = node_ptr };
+        }
+        queue.tail = node_ptr;
+        // Must be visible to the fast path's lock-free peek before we drop
+        // the lock and park: once `wait` is called below, this node is only
+        // touched under the queue lock, by a later waiter linking itself in
+        // behind it or by `wake_waiters`.
+        self.has_waiters.store(true, Release);
+        drop(queue);
+
+        while node.futex.load(Acquire) == 0 {
+            wait(&node.futex, 0);
+        }
+
+        Permit {
+            state: n,
+            semaphore: self,
+        }
+    }
+
+    pub fn release(permit: Permit) {
+        drop(permit);
+    }
+
+    /// Walk the queue from the front, handing permits to whichever
+    /// head waiters can be fully satisfied. Stops at the first waiter
+    /// that cannot yet be served, so later, smaller waiters never jump
+    /// ahead of it.
+    fn wake_waiters(&self) {
+        let mut queue = self.waiters.lock();
+        loop {
+            let head = queue.head;
+            if head.is_null() {
+                return;
+            }
+
+            // SAFETY: `head` is only ever unlinked under the queue lock,
+            // which we hold here.
+            let needed = unsafe { (*head).needed };
+            let mut s = self.state.load(Relaxed);
+            loop {
+                if s < needed {
+                    return;
+                }
+                match self.state.compare_exchange_weak(s, s - needed, Acquire, Relaxed) {
+                    Ok(_) => break,
+                    Err(e) => s = e,
+                }
+            }
+
+            // SAFETY: see above.
+            unsafe {
+                let next = (*head).next;
+                queue.head = next;
+                if next.is_null() {
+                    queue.tail = null_mut();
+                    self.has_waiters.store(false, Release);
+                }
+                (*head).futex.store(1, Release);
+                wake_one(&(*head).futex);
+            }
+        }
+    }
+
+    fn get_count(&self) -> u32 {
+        self.state.load(Relaxed)
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.semaphore.state.fetch_add(self.state, Release);
+        self.semaphore.wake_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{mpsc, Arc},
+        thread,
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[test]
+    fn new() {
+        let semaphore = Arc::new(FairSemaphore::new(3));
+        assert_eq!(semaphore.get_count(), 3);
+    }
+
+    #[test]
+    fn all_permits_returned_under_contention() {
+        let semaphore = Arc::new(FairSemaphore::new(1));
+        thread::scope(|s| {
+            for _ in 0..30 {
+                let semaphore = semaphore.clone();
+                s.spawn(move || {
+                    let permit = semaphore.aquire_n(1);
+                    std::hint::black_box(&semaphore);
+                    FairSemaphore::release(permit);
+                });
+            }
+        });
+        assert_eq!(semaphore.get_count(), 1);
+    }
+
+    #[test]
+    fn large_request_is_not_starved_by_small_ones() {
+        let semaphore = Arc::new(FairSemaphore::new(10));
+        thread::scope(|s| {
+            let big = semaphore.clone();
+            s.spawn(move || {
+                let permit = big.aquire_n(10);
+                FairSemaphore::release(permit);
+            });
+            for _ in 0..50 {
+                let semaphore = semaphore.clone();
+                s.spawn(move || {
+                    let permit = semaphore.aquire_n(1);
+                    FairSemaphore::release(permit);
+                });
+            }
+        });
+        assert_eq!(semaphore.get_count(), 10);
+    }
+
+    #[test]
+    fn large_request_is_not_starved_by_sustained_small_churn() {
+        let semaphore = Arc::new(FairSemaphore::new(10));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        // Steady uncontended small-request traffic, running both before
+        // and during the large request's wait below: each of these
+        // threads only ever asks for a single permit, so the fast path
+        // alone could serve all of them forever without the large
+        // request ever being allowed to reach the front of the queue.
+        let churn: Vec<_> = (0..8)
+            .map(|_| {
+                let semaphore = semaphore.clone();
+                let stop = stop.clone();
+                thread::spawn(move || {
+                    while !stop.load(Acquire) {
+                        let permit = semaphore.aquire_n(1);
+                        FairSemaphore::release(permit);
+                    }
+                })
+            })
+            .collect();
+
+        thread::sleep(Duration::from_millis(20));
+
+        // Run the large request on its own thread and report back over a
+        // channel rather than just calling `aquire_n` inline, so a
+        // starved large request times out this test instead of hanging
+        // it (and the rest of the suite) forever.
+        let (served_tx, served_rx) = mpsc::channel();
+        let big = semaphore.clone();
+        thread::spawn(move || {
+            let permit = big.aquire_n(10);
+            let _ = served_tx.send(());
+            FairSemaphore::release(permit);
+        });
+
+        let served = served_rx.recv_timeout(Duration::from_secs(2)).is_ok();
+        stop.store(true, Release);
+        for handle in churn {
+            handle.join().unwrap();
+        }
+
+        assert!(
+            served,
+            "large request was starved by continuous single-permit churn"
+        );
+    }
+}